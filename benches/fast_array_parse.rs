@@ -0,0 +1,32 @@
+//! Guards the `memchr`-accelerated bulk-array fast path against regressions:
+//! parses a synthetic `method`-style file whose single array param has 1M
+//! elements, which is the shape that made `push_atoms_from_line` dominate in
+//! profiling.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use jcamp_rs::parse_paravision_params;
+
+const N: usize = 1_000_000;
+
+fn synthetic_method_file() -> String {
+    let mut s = String::with_capacity(N * 7);
+    s.push_str("##TITLE=synthetic\n");
+    s.push_str("##JCAMPDX=4.24\n");
+    s.push_str(&format!("##$BIG_ARRAY=( {N} )\n"));
+    for i in 0..N {
+        s.push_str(&i.to_string());
+        s.push(' ');
+    }
+    s.push('\n');
+    s
+}
+
+fn bench_large_array(c: &mut Criterion) {
+    let data = synthetic_method_file();
+    c.bench_function("parse_paravision_params/1m_element_array", |b| {
+        b.iter(|| parse_paravision_params(data.as_bytes()).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_large_array);
+criterion_main!(benches);
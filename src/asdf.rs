@@ -0,0 +1,265 @@
+// Decoder for JCAMP-DX ASCII Squeezed Difference Form (ASDF), the compression
+// scheme used by ##XYDATA=/##PEAK TABLE= blocks (as opposed to the ParaVision
+// ##$... parameter records handled elsewhere in this crate).
+
+use crate::PvError;
+
+// Tolerance for comparing the restated Y-value check against the previously
+// reconstructed ordinate; ASDF data is usually integral but needn't be.
+const ASDF_CHECK_EPS: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy)]
+enum AsdfToken {
+    Pac(f64),
+    Sqz(f64),
+    Dif(f64),
+    Dup(usize),
+}
+
+fn sqz_digit(c: char) -> Option<(f64, i64)> {
+    match c {
+        '@' => Some((1.0, 0)),
+        'A'..='I' => Some((1.0, c as i64 - 'A' as i64 + 1)),
+        'a'..='i' => Some((-1.0, c as i64 - 'a' as i64 + 1)),
+        _ => None,
+    }
+}
+
+fn dif_digit(c: char) -> Option<(f64, i64)> {
+    match c {
+        '%' => Some((1.0, 0)),
+        'J'..='R' => Some((1.0, c as i64 - 'J' as i64 + 1)),
+        'j'..='r' => Some((-1.0, c as i64 - 'j' as i64 + 1)),
+        _ => None,
+    }
+}
+
+fn dup_digit(c: char) -> Option<usize> {
+    match c {
+        'S'..='Z' => Some((c as usize - 'S' as usize) + 2),
+        's' => Some(10),
+        _ => None,
+    }
+}
+
+// Reads the plain digits (and an optional decimal point) following a SQZ/DIF
+// leading character, returning the signed value and the index just past the
+// last digit consumed.
+fn read_signed_number(chars: &[char], mut i: usize, sign: f64, first_digit: i64) -> (f64, usize) {
+    let mut digits = first_digit.to_string();
+    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+        digits.push(chars[i]);
+        i += 1;
+    }
+    (sign * digits.parse::<f64>().unwrap_or(0.0), i)
+}
+
+// Reads the plain digits following a DUP leading character, extending the
+// repeat count decimally (e.g. `T` then `5` means repeat 35 times).
+fn read_dup_count(chars: &[char], mut i: usize, first_digit: usize) -> (usize, usize) {
+    let mut digits = first_digit.to_string();
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        digits.push(chars[i]);
+        i += 1;
+    }
+    (digits.parse().unwrap_or(first_digit), i)
+}
+
+// Reads a plain (optionally signed) decimal number in PAC form.
+fn read_pac_number(chars: &[char], start: usize) -> (f64, usize) {
+    let mut i = start;
+    if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+        i += 1;
+    }
+    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+        i += 1;
+    }
+    let s: String = chars[start..i].iter().collect();
+    (s.parse().unwrap_or(0.0), i)
+}
+
+// Splits the X checkpoint off the front of an ASDF data line. It's a plain
+// decimal number, but unlike PAC-encoded Y data it needn't be followed by
+// whitespace -- SQZ/DIF values are self-delimiting via their leading
+// sign+digit character, so real files often run the checkpoint straight into
+// the first Y token (e.g. `400.0A2345`).
+fn split_x_checkpoint(line: &str) -> Option<(f64, &str)> {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+        i += 1;
+    }
+    let digits_start = i;
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < bytes.len() && bytes[i] == b'.' {
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i == digits_start {
+        return None;
+    }
+    line[..i].parse::<f64>().ok().map(|x| (x, &line[i..]))
+}
+
+fn tokenize_asdf_data(s: &str, line_no: usize) -> Result<Vec<AsdfToken>, PvError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if let Some((sign, d)) = sqz_digit(c) {
+            let (v, next) = read_signed_number(&chars, i + 1, sign, d);
+            tokens.push(AsdfToken::Sqz(v));
+            i = next;
+        } else if let Some((sign, d)) = dif_digit(c) {
+            let (v, next) = read_signed_number(&chars, i + 1, sign, d);
+            tokens.push(AsdfToken::Dif(v));
+            i = next;
+        } else if let Some(d) = dup_digit(c) {
+            let (count, next) = read_dup_count(&chars, i + 1, d);
+            tokens.push(AsdfToken::Dup(count));
+            i = next;
+        } else if c == '+' || c == '-' || c.is_ascii_digit() {
+            let (v, next) = read_pac_number(&chars, i);
+            tokens.push(AsdfToken::Pac(v));
+            i = next;
+        } else {
+            return Err(PvError::Parse(format!(
+                "unexpected character `{c}` in ASDF data on line {line_no}"
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+// Decodes a ##XYDATA=/##PEAK TABLE= ASDF-compressed data block into its
+// ordinate values. Each line begins with an X checkpoint (not stored)
+// followed by Y ordinates in any mix of PAC, SQZ, DIF, and DUP form; after a
+// DIF line, the next line's first token restates the last ordinate as a
+// check and is verified then dropped rather than stored.
+pub fn parse_jcamp_xydata(block: &str) -> Result<Vec<f64>, PvError> {
+    let mut y = Vec::new();
+    let mut prev: Option<f64> = None;
+    let mut expect_check: Option<f64> = None;
+
+    for (idx, raw_line) in block.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (_x_checkpoint, rest) = split_x_checkpoint(line).ok_or_else(|| {
+            PvError::Parse(format!("bad X checkpoint on ASDF line {line_no}: {line:?}"))
+        })?;
+
+        let tokens = tokenize_asdf_data(rest, line_no)?;
+
+        let mut first_y_on_line = true;
+        let mut line_used_dif = false;
+        for tok in tokens {
+            if let AsdfToken::Dup(count) = tok {
+                let last = prev.ok_or_else(|| {
+                    PvError::Parse(format!("DUP repeat with no preceding value on ASDF line {line_no}"))
+                })?;
+                for _ in 1..count {
+                    y.push(last);
+                }
+                first_y_on_line = false;
+                continue;
+            }
+
+            let value = match tok {
+                AsdfToken::Pac(v) | AsdfToken::Sqz(v) => v,
+                AsdfToken::Dif(delta) => {
+                    line_used_dif = true;
+                    let base = prev.ok_or_else(|| {
+                        PvError::Parse(format!("DIF ordinate with no preceding value on ASDF line {line_no}"))
+                    })?;
+                    base + delta
+                }
+                AsdfToken::Dup(_) => unreachable!("handled above"),
+            };
+
+            if first_y_on_line {
+                first_y_on_line = false;
+                if let Some(expected) = expect_check {
+                    if (value - expected).abs() > ASDF_CHECK_EPS {
+                        return Err(PvError::Parse(format!(
+                            "ASDF Y-value check failed on line {line_no}: expected {expected}, got {value}"
+                        )));
+                    }
+                    prev = Some(value);
+                    continue;
+                }
+            }
+
+            y.push(value);
+            prev = Some(value);
+        }
+
+        expect_check = if line_used_dif { prev } else { None };
+    }
+
+    Ok(y)
+}
+
+// Pairs ordinates decoded by parse_jcamp_xydata with their X coordinates,
+// reconstructing x = FIRSTX + i * DELTAX and scaling each ordinate by the
+// block's YFACTOR LDR.
+pub fn scale_xydata(y: &[f64], first_x: f64, delta_x: f64, y_factor: f64) -> impl Iterator<Item = (f64, f64)> + '_ {
+    y.iter()
+        .enumerate()
+        .map(move |(i, &raw)| (first_x + i as f64 * delta_x, raw * y_factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_sqz_and_dif_across_lines() {
+        // Line 1: SQZ(2) then DIF(+2), DIF(-1) -> 2, 4, 3.
+        // Line 2 restates the check value 3, then one more DIF(+2) -> 5.
+        let y = parse_jcamp_xydata("0 B K j\n2 3 K\n").unwrap();
+        assert_eq!(y, vec![2.0, 4.0, 3.0, 5.0]);
+    }
+
+    #[test]
+    fn decodes_dup_as_repeats_of_previous_value() {
+        // B = SQZ(2), T = DUP(3) -> two more copies of the previous value.
+        let y = parse_jcamp_xydata("0 B T\n").unwrap();
+        assert_eq!(y, vec![2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn decodes_pac_form() {
+        let y = parse_jcamp_xydata("0 +5 -3 10\n").unwrap();
+        assert_eq!(y, vec![5.0, -3.0, 10.0]);
+    }
+
+    #[test]
+    fn checkpoint_mismatch_is_an_error() {
+        let err = parse_jcamp_xydata("0 B K j\n2 999 K\n").unwrap_err();
+        match err {
+            PvError::Parse(msg) => assert!(msg.contains("check failed"), "unexpected message: {msg}"),
+            other => panic!("expected PvError::Parse, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn sqz_data_need_not_be_space_delimited_from_the_checkpoint() {
+        // No whitespace between the X checkpoint and the SQZ-encoded value:
+        // the leading sign+digit character is self-delimiting.
+        let y = parse_jcamp_xydata("400.0A2345\n").unwrap();
+        assert_eq!(y, vec![12345.0]);
+    }
+}
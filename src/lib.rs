@@ -1,10 +1,13 @@
-#[derive(Debug, Clone)]
+pub mod asdf;
+pub use asdf::{parse_jcamp_xydata, scale_xydata};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PvParams {
     pub meta: HashMap<String, String>, // e.g. TITLE, JCAMPDX, DATATYPE...
     pub params: HashMap<String, PvValue>, // everything under ##$...
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PvValue {
     Scalar(PvAtom),
     Array { dims: Vec<usize>, items: Vec<PvAtom> },
@@ -29,7 +32,7 @@ impl PvValue {
 
     pub fn to_usize(&self) -> Option<usize> {
         match self {
-            PvValue::Scalar(value) => Some(value.clone().into()),
+            PvValue::Scalar(value) => Some(value.clone().try_into().expect("cannot parse atom as usize")),
             PvValue::Array { .. } => None,
             PvValue::Str(_) => None,
         }
@@ -37,40 +40,200 @@ impl PvValue {
 
     pub fn to_vec_usize(&self) -> Option<Vec<usize>> {
         match self {
-            PvValue::Scalar(atom) => Some(vec![atom.clone().into()]),
-            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.into()).collect()),
+            PvValue::Scalar(atom) => Some(vec![atom.clone().try_into().expect("cannot parse atom as usize")]),
+            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.try_into().expect("cannot parse atom as usize")).collect()),
             PvValue::Str(_) => None
         }
     }
 
     pub fn to_vec_f64(&self) -> Option<Vec<f64>> {
         match self {
-            PvValue::Scalar(atom) => Some(vec![atom.clone().into()]),
-            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.into()).collect()),
+            PvValue::Scalar(atom) => Some(vec![atom.clone().try_into().expect("cannot parse atom as f64")]),
+            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.try_into().expect("cannot parse atom as f64")).collect()),
             PvValue::Str(_) => None
         }
     }
 
     pub fn to_vec_bool(&self) -> Option<Vec<bool>> {
         match self {
-            PvValue::Scalar(atom) => Some(vec![atom.clone().into()]),
-            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.into()).collect()),
+            PvValue::Scalar(atom) => Some(vec![atom.clone().try_into().expect("cannot parse atom as bool")]),
+            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.try_into().expect("cannot parse atom as bool")).collect()),
             PvValue::Str(_) => None
         }
     }
 
     pub fn to_vec_i64(&self) -> Option<Vec<i64>> {
         match self {
-            PvValue::Scalar(atom) => Some(vec![atom.clone().into()]),
-            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.into()).collect()),
+            PvValue::Scalar(atom) => Some(vec![atom.clone().try_into().expect("cannot parse atom as i64")]),
+            PvValue::Array { items, .. } => Some(items.iter().cloned().map(|i| i.try_into().expect("cannot parse atom as i64")).collect()),
             PvValue::Str(_) => None
         }
     }
 
+    /// Fallible counterpart to [`PvValue::to_usize`]: reports a [`PvError::Parse`]
+    /// instead of panicking when the underlying atom isn't a valid `usize`.
+    pub fn try_to_usize(&self) -> Result<usize, PvError> {
+        match self {
+            PvValue::Scalar(atom) => atom.clone().try_into(),
+            PvValue::Array { .. } => Err(PvError::Parse("expected a scalar, found an array".to_string())),
+            PvValue::Str(s) => Err(PvError::Parse(format!("expected a scalar, found the string {s:?}"))),
+        }
+    }
+
+    /// Fallible counterpart to [`PvValue::to_vec_f64`]: reports a [`PvError::Parse`]
+    /// instead of panicking when any element can't be parsed as `f64`.
+    pub fn try_to_vec_f64(&self) -> Result<Vec<f64>, PvError> {
+        match self {
+            PvValue::Scalar(atom) => Ok(vec![atom.clone().try_into()?]),
+            PvValue::Array { items, .. } => items.iter().cloned().map(TryInto::try_into).collect(),
+            PvValue::Str(s) => Err(PvError::Parse(format!("expected numeric data, found the string {s:?}"))),
+        }
+    }
+
+    /// The dims of an `Array` value (e.g. `[n_read, n_proj]` for `ACQ_size`),
+    /// or `None` for scalars and strings.
+    pub fn shape(&self) -> Option<&[usize]> {
+        match self {
+            PvValue::Array { dims, .. } => Some(dims.as_slice()),
+            PvValue::Scalar(_) | PvValue::Str(_) => None,
+        }
+    }
+
+    /// Looks up the atom at `coord` in an `Array` value, mapping the
+    /// row-major coordinate tuple to the flat `items` index. Returns `None`
+    /// if `self` isn't an `Array`, `coord`'s rank doesn't match `dims`, or any
+    /// coordinate is out of range.
+    pub fn get_nd(&self, coord: &[usize]) -> Option<&PvAtom> {
+        let (dims, items) = match self {
+            PvValue::Array { dims, items } => (dims, items),
+            PvValue::Scalar(_) | PvValue::Str(_) => return None,
+        };
+        if coord.len() != dims.len() {
+            return None;
+        }
+        let mut flat = 0usize;
+        for (&c, &d) in coord.iter().zip(dims.iter()) {
+            if c >= d {
+                return None;
+            }
+            flat = flat * d + c;
+        }
+        items.get(flat)
+    }
+
+    /// Iterates a 2-D `Array` value of shape `(n_rows, row_len)` as `n_rows`
+    /// slices of length `row_len`. Returns `None` for anything that isn't a
+    /// rank-2 array, and for rank-2 arrays whose `row_len` is zero.
+    pub fn rows(&self) -> Option<std::slice::Chunks<'_, PvAtom>> {
+        match self {
+            PvValue::Array { dims, items } if dims.len() == 2 && dims[1] > 0 => Some(items.chunks(dims[1])),
+            _ => None,
+        }
+    }
+
+    /// Iterates an `Array` value's flattened `items` in chunks sized to its
+    /// last dim, e.g. a `(frames, coils, n_read)` array yields `frames *
+    /// coils` chunks of `n_read` atoms. Returns `None` for scalars, strings,
+    /// and arrays whose last dim is zero.
+    pub fn chunks_by_last_dim(&self) -> Option<std::slice::Chunks<'_, PvAtom>> {
+        match self {
+            PvValue::Array { dims, items } => match dims.last() {
+                Some(&last) if last > 0 => Some(items.chunks(last)),
+                _ => None,
+            },
+            PvValue::Scalar(_) | PvValue::Str(_) => None,
+        }
+    }
+
+}
+
+impl PvParams {
+    /// Serializes this `PvParams` back into a ParaVision-style JCAMP-DX text,
+    /// suitable for writing to an `acqp`/`method` file and re-reading with
+    /// [`parse_paravision_params`].
+    pub fn to_jcamp_string(&self) -> String {
+        let mut buf = Vec::new();
+        write_paravision_params(&mut buf, self).expect("writing to an in-memory Vec<u8> cannot fail");
+        String::from_utf8(buf).expect("serialized JCAMP output is always valid UTF-8")
+    }
+}
+
+// Writes `params` out as a ParaVision-style JCAMP-DX parameter file: meta
+// LDRs (##TITLE=, ##JCAMPDX=, ...) first, then the ##$KEY= param records.
+// Inverse of parse_paravision_params.
+pub fn write_paravision_params<W: Write>(mut writer: W, params: &PvParams) -> io::Result<()> {
+    // ParaVision always leads with these two LDRs when present.
+    for key in ["TITLE", "JCAMPDX"] {
+        if let Some(v) = params.meta.get(key) {
+            writeln!(writer, "##{key}={v}")?;
+        }
+    }
+    for (k, v) in &params.meta {
+        if k == "TITLE" || k == "JCAMPDX" {
+            continue;
+        }
+        writeln!(writer, "##{k}={v}")?;
+    }
+
+    for (key, value) in &params.params {
+        write_param(&mut writer, key, value)?;
+    }
+
+    Ok(())
+}
+
+// ParaVision wraps long array data across multiple lines at this column width.
+const ARRAY_WRAP_WIDTH: usize = 80;
+
+fn write_param<W: Write>(writer: &mut W, key: &str, value: &PvValue) -> io::Result<()> {
+    match value {
+        PvValue::Scalar(atom) => writeln!(writer, "##${key}={}", atom_token(atom)),
+        PvValue::Str(s) => {
+            // The parser only recognizes a `<...>` line as a `Str` when it's
+            // preceded by a dims header, the same as an array; without one
+            // it re-parses as a scalar `Text` atom holding the raw `<...>`.
+            let len = s.chars().count().max(1);
+            writeln!(writer, "##${key}=( {len} )")?;
+            writeln!(writer, "<{s}>")
+        }
+        PvValue::Array { dims, items } => {
+            let dims = dims.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ");
+            writeln!(writer, "##${key}=( {dims} )")?;
+            write_wrapped_atoms(writer, items)
+        }
+    }
+}
+
+// `{}` prints an integral f64 without a fractional part (`2.0` as `2`), which
+// would re-parse as an Int; `{:?}` keeps the `.0`.
+fn atom_token(atom: &PvAtom) -> String {
+    match atom {
+        PvAtom::Float(f) => format!("{f:?}"),
+        other => other.to_string(),
+    }
+}
+
+fn write_wrapped_atoms<W: Write>(writer: &mut W, items: &[PvAtom]) -> io::Result<()> {
+    let mut line = String::new();
+    for atom in items {
+        let tok = atom_token(atom);
+        if !line.is_empty() && line.len() + 1 + tok.len() > ARRAY_WRAP_WIDTH {
+            writeln!(writer, "{line}")?;
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&tok);
+    }
+    if !line.is_empty() {
+        writeln!(writer, "{line}")?;
+    }
+    Ok(())
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PvAtom {
     Bool(bool),   // Yes/No/True/False
     Int(i64),
@@ -89,166 +252,271 @@ impl Display for PvAtom {
     }
 }
 
-impl From<PvAtom> for f64 {
-    fn from(val: PvAtom) -> Self {
-        match val  {
-            PvAtom::Bool(true) => 1.0,
-            PvAtom::Bool(false) => 0.0,
-            PvAtom::Int(i) => i as f64,
-            PvAtom::Float(f) => f,
-            PvAtom::Text(s) => s.parse().expect("cannot parse string as float"),
+impl TryFrom<PvAtom> for f64 {
+    type Error = PvError;
+    fn try_from(val: PvAtom) -> Result<Self, Self::Error> {
+        match val {
+            PvAtom::Bool(true) => Ok(1.0),
+            PvAtom::Bool(false) => Ok(0.0),
+            PvAtom::Int(i) => Ok(i as f64),
+            PvAtom::Float(f) => Ok(f),
+            PvAtom::Text(s) => s.parse().map_err(|_| PvError::Parse(format!("cannot parse {s:?} as f64"))),
         }
     }
 }
 
-impl From<PvAtom> for usize {
-    fn from(val: PvAtom) -> usize {
+impl TryFrom<PvAtom> for usize {
+    type Error = PvError;
+    fn try_from(val: PvAtom) -> Result<Self, Self::Error> {
         match val {
-            PvAtom::Bool(b) => {if b {1} else {0}},
-            PvAtom::Int(i) => {i as usize},
-            PvAtom::Float(f) => {f as usize},
-            PvAtom::Text(s) => s.parse().expect("cannot parse string as usize"),
+            PvAtom::Bool(b) => Ok(if b { 1 } else { 0 }),
+            PvAtom::Int(i) => Ok(i as usize),
+            PvAtom::Float(f) => Ok(f as usize),
+            PvAtom::Text(s) => s.parse().map_err(|_| PvError::Parse(format!("cannot parse {s:?} as usize"))),
         }
     }
 }
 
-impl From<PvAtom> for i64 {
-    fn from(val: PvAtom) -> i64 {
+impl TryFrom<PvAtom> for i64 {
+    type Error = PvError;
+    fn try_from(val: PvAtom) -> Result<Self, Self::Error> {
         match val {
-            PvAtom::Bool(b) => {if b {1} else {0}},
-            PvAtom::Int(i) => {i},
-            PvAtom::Float(f) => {f as i64},
-            PvAtom::Text(s) => s.parse().expect("cannot parse string as usize"),
+            PvAtom::Bool(b) => Ok(if b { 1 } else { 0 }),
+            PvAtom::Int(i) => Ok(i),
+            PvAtom::Float(f) => Ok(f as i64),
+            PvAtom::Text(s) => s.parse().map_err(|_| PvError::Parse(format!("cannot parse {s:?} as i64"))),
         }
     }
 }
 
-impl From<PvAtom> for bool {
-    fn from(val: PvAtom) -> bool {
+impl TryFrom<PvAtom> for bool {
+    type Error = PvError;
+    fn try_from(val: PvAtom) -> Result<Self, Self::Error> {
         match val {
-            PvAtom::Bool(b) => b,
-            PvAtom::Int(i) => {i.abs() > 0},
-            PvAtom::Float(f) => {f.abs() > 0.},
-            PvAtom::Text(s) => s.parse().expect("cannot parse string as bool"),
+            PvAtom::Bool(b) => Ok(b),
+            PvAtom::Int(i) => Ok(i.abs() > 0),
+            PvAtom::Float(f) => Ok(f.abs() > 0.),
+            PvAtom::Text(s) => s.parse().map_err(|_| PvError::Parse(format!("cannot parse {s:?} as bool"))),
         }
     }
 }
 
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 
 #[derive(Debug)]
 pub enum PvError {
     Io(io::Error),
     Parse(String),
+    /// EOF was reached while `key`'s array/string data (declared on `line`) was
+    /// still short of the element count its dims header promised.
+    UnexpectedEof { key: String, line: usize, expected: usize, got: usize },
+    /// `##$KEY=( ... )` on `line` could not be parsed as a dims header.
+    BadDims { key: String, line: usize, raw: String },
+    /// A new `##` record started before `key` (declared on `line`) had
+    /// consumed as many elements as its dims header promised.
+    ShortArray { key: String, line: usize, need: usize, got: usize },
 }
 
 impl From<io::Error> for PvError {
     fn from(e: io::Error) -> Self { PvError::Io(e) }
 }
 
+impl Display for PvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PvError::Io(e) => write!(f, "io error: {e}"),
+            PvError::Parse(msg) => write!(f, "{msg}"),
+            PvError::UnexpectedEof { key, line, expected, got } => write!(
+                f, "unexpected EOF: `{key}` (declared at line {line}) expected {expected} elements, got {got}"
+            ),
+            PvError::BadDims { key, line, raw } => write!(
+                f, "malformed dims header for `{key}` at line {line}: `{raw}`"
+            ),
+            PvError::ShortArray { key, line, need, got } => write!(
+                f, "new record started before `{key}` (declared at line {line}) was filled: needed {need} elements, got {got}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PvError {}
+
 #[derive(Debug)]
 enum Pending {
     // After seeing ##$KEY=( dims... ) we read the next non-comment line and decide:
     // - if it is <...> => Str
     // - else => Array and we treat that line as the first chunk of array data
-    Dims { key: String, dims: Vec<usize> },
-    Array { key: String, dims: Vec<usize>, need: usize, items: Vec<PvAtom> },
+    Dims { key: String, dims: Vec<usize>, line: usize },
+    Array { key: String, dims: Vec<usize>, need: usize, items: Vec<PvAtom>, line: usize },
 }
 
 
-pub fn parse_paravision_params<R: BufRead>(mut reader: R) -> Result<PvParams, PvError> {
-    let mut out = PvParams { meta: HashMap::new(), params: HashMap::new() };
+// Streams ##$KEY=... records out one at a time instead of collecting the
+// whole file into a HashMap up front, so a caller can find/take_while a few
+// keys out of a method file and stop without parsing the rest.
+// Meta LDRs accumulate in `meta` as they're seen, readable once iteration ends.
+pub struct ParamReader<R: BufRead> {
+    reader: R,
+    line: String,
+    line_no: usize,
+    pending: Option<Pending>,
+    done: bool,
+    pub meta: HashMap<String, String>,
+}
 
-    let mut pending: Option<Pending> = None;
+impl<R: BufRead> ParamReader<R> {
+    pub fn new(reader: R) -> Self {
+        ParamReader {
+            reader,
+            line: String::new(),
+            line_no: 0,
+            pending: None,
+            done: false,
+            meta: HashMap::new(),
+        }
+    }
+}
 
-    let mut line = String::new();
-    while {
-        line.clear();
-        reader.read_line(&mut line)?
-    } != 0 {
-        let raw = line.trim_end_matches(&['\r', '\n'][..]);
-        let s = raw.trim();
-        if s.is_empty() || s.starts_with("$$") {
-            continue;
+impl<R: BufRead> Iterator for ParamReader<R> {
+    type Item = Result<(String, PvValue), PvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
         }
 
-        // If we are collecting data for a previous header, handle that first.
-        if let Some(p) = pending.take() {
-            match p {
-                Pending::Dims { key, dims } => {
-                    // Decide string vs array based on this line
-                    if let Some(txt) = parse_angle_brackets(s) {
-                        out.params.insert(key, PvValue::Str(txt));
-                    } else {
+        loop {
+            self.line.clear();
+            let n = match self.reader.read_line(&mut self.line) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(PvError::Io(e)));
+                }
+            };
+
+            if n == 0 {
+                self.done = true;
+                return self.pending.take().map(|p| Err(match p {
+                    Pending::Dims { key, dims, line } => PvError::UnexpectedEof {
+                        key, line, expected: dims.iter().product(), got: 0,
+                    },
+                    Pending::Array { key, need, items, line, .. } => PvError::UnexpectedEof {
+                        key, line, expected: need, got: items.len(),
+                    },
+                }));
+            }
+
+            self.line_no += 1;
+            let raw = self.line.trim_end_matches(&['\r', '\n'][..]);
+            let s = raw.trim();
+            if s.is_empty() || s.starts_with("$$") {
+                continue;
+            }
+
+            // If we are collecting data for a previous header, handle that first.
+            if let Some(p) = self.pending.take() {
+                // A new record starting before the pending data was fully consumed
+                // means the file lied about how many elements it had.
+                if s.starts_with("##") {
+                    self.done = true;
+                    return Some(Err(match p {
+                        Pending::Dims { key, dims, line } => PvError::ShortArray {
+                            key, line, need: dims.iter().product(), got: 0,
+                        },
+                        Pending::Array { key, need, items, line, .. } => PvError::ShortArray {
+                            key, line, need, got: items.len(),
+                        },
+                    }));
+                }
+
+                match p {
+                    Pending::Dims { key, dims, line: decl_line } => {
+                        // Decide string vs array based on this line
+                        if let Some(txt) = parse_angle_brackets(s) {
+                            return Some(Ok((key, PvValue::Str(txt))));
+                        }
                         let need = dims.iter().product();
-                        let mut items = Vec::new();
-                        push_atoms_from_line(s, &mut items);
+                        let mut items = Vec::with_capacity(need);
+                        push_atoms_from_line_fast(s, &mut items);
 
                         if items.len() >= need {
                             items.truncate(need);
-                            out.params.insert(key, PvValue::Array { dims, items });
-                        } else {
-                            pending = Some(Pending::Array { key, dims, need, items });
+                            return Some(Ok((key, PvValue::Array { dims, items })));
                         }
+                        self.pending = Some(Pending::Array { key, dims, need, items, line: decl_line });
+                        continue;
                     }
-                    continue;
-                }
-                Pending::Array { key, dims, need, mut items } => {
-                    push_atoms_from_line(s, &mut items);
-                    if items.len() >= need {
-                        items.truncate(need);
-                        out.params.insert(key, PvValue::Array { dims, items });
-                    } else {
-                        pending = Some(Pending::Array { key, dims, need, items });
+                    Pending::Array { key, dims, need, mut items, line: decl_line } => {
+                        push_atoms_from_line_fast(s, &mut items);
+                        if items.len() >= need {
+                            items.truncate(need);
+                            return Some(Ok((key, PvValue::Array { dims, items })));
+                        }
+                        self.pending = Some(Pending::Array { key, dims, need, items, line: decl_line });
+                        continue;
                     }
-                    continue;
                 }
             }
-        }
 
+            // Not pending: parse a record line.
+            if s.starts_with("##") {
+                let rest = &s[2..];
 
-        // Not pending: parse a record line.
-        if s.starts_with("##") {
-            let rest = &s[2..];
+                // Meta LDR: ##TITLE=..., ##JCAMPDX=...
+                if !rest.starts_with('$') {
+                    let (k, v) = split_key_value(rest);
+                    self.meta.insert(normalize_key(k), v.trim().to_string());
+                    continue;
+                }
 
-            // Meta LDR: ##TITLE=..., ##JCAMPDX=...
-            if !rest.starts_with('$') {
+                // Param record: ##$NAME=...
+                // NAME can include underscores etc.
+                let rest = &rest[1..]; // drop '$'
                 let (k, v) = split_key_value(rest);
-                out.meta.insert(normalize_key(k), v.trim().to_string());
-                continue;
-            }
-
-            // Param record: ##$NAME=...
-            // NAME can include underscores etc.
-            let rest = &rest[1..]; // drop '$'
-            let (k, v) = split_key_value(rest);
-            let key = k.trim().to_string();
-            let v = v.trim();
+                let key = k.trim().to_string();
+                let v = v.trim();
+
+                // Array or bracket-string header?
+                if v.starts_with('(') {
+                    match parse_dims(v) {
+                        Some(dims) => {
+                            // A zero-product shape (e.g. `( 0 )`) has no data
+                            // line to disambiguate Str vs Array against --
+                            // it's simply an empty array.
+                            if dims.iter().product::<usize>() == 0 {
+                                return Some(Ok((key, PvValue::Array { dims, items: Vec::new() })));
+                            }
+                            self.pending = Some(Pending::Dims { key, dims, line: self.line_no });
+                            continue;
+                        }
+                        None => {
+                            self.done = true;
+                            return Some(Err(PvError::BadDims { key, line: self.line_no, raw: v.to_string() }));
+                        }
+                    }
+                }
 
-            // Array or bracket-string header?
-            if let Some(dims) = parse_dims(v) {
-                pending = Some(Pending::Dims { key, dims });
-                continue;
+                // Scalar param
+                return Some(Ok((key, PvValue::Scalar(parse_atom(v)))));
             }
 
-
-            // Scalar param
-            out.params.insert(key, PvValue::Scalar(parse_atom(v)));
-            continue;
+            // If we hit here: a non-## line with no pending state. Usually ignorable.
+            // But: it can happen if someone wrote a continuation without declaring dims.
         }
-
-        // If we hit here: a non-## line with no pending state. Usually ignorable.
-        // But: it can happen if someone wrote a continuation without declaring dims.
     }
+}
 
-    // If EOF while pending: try to finish gracefully
-    if let Some(p) = pending {
-        return Err(PvError::Parse(format!("Unexpected EOF while parsing pending record: {p:?}")));
+pub fn parse_paravision_params<R: BufRead>(reader: R) -> Result<PvParams, PvError> {
+    let mut rdr = ParamReader::new(reader);
+    let mut params = HashMap::new();
+    for item in &mut rdr {
+        let (key, value) = item?;
+        params.insert(key, value);
     }
-
-    Ok(out)
+    Ok(PvParams { meta: rdr.meta, params })
 }
 
 fn split_key_value(s: &str) -> (&str, &str) {
@@ -287,10 +555,45 @@ fn parse_angle_brackets(s: &str) -> Option<String> {
     Some(s[1..s.len()-1].to_string())
 }
 
-fn push_atoms_from_line(line: &str, out: &mut Vec<PvAtom>) {
-    for tok in line.split_whitespace() {
-        out.push(parse_atom(tok));
+// Bulk array data dominates parse time on large `method` files (arrays with
+// tens of thousands to millions of elements), so this path avoids the
+// allocating `split_whitespace` iterator in favor of scanning raw bytes with
+// `memchr`, and parses each token straight into an `i64`/`f64` without ever
+// building a fallback `String` unless the token turns out to be non-numeric.
+fn push_atoms_from_line_fast(line: &str, out: &mut Vec<PvAtom>) {
+    let bytes = line.as_bytes();
+    let mut pos = 0;
+    let len = bytes.len();
+    while pos < len {
+        while pos < len && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos >= len {
+            break;
+        }
+        // Space and tab cover every separator these files actually use;
+        // `is_ascii_whitespace()` (used above to skip runs of them) is the
+        // full ASCII whitespace set, so a run of mixed space/tab is still
+        // skipped correctly even though this scan only looks for those two.
+        let end = memchr::memchr2(b' ', b'\t', &bytes[pos..]).map_or(len, |i| pos + i);
+        out.push(parse_atom_bytes(&bytes[pos..end]));
+        pos = end;
+    }
+}
+
+fn parse_atom_bytes(tok: &[u8]) -> PvAtom {
+    // Tokens are ASCII in every file we've seen; fall back to the general
+    // (allocating) path for anything that isn't, same as a non-numeric token.
+    let Ok(s) = std::str::from_utf8(tok) else {
+        return PvAtom::Text(String::from_utf8_lossy(tok).into_owned());
+    };
+    if let Ok(i) = s.parse::<i64>() {
+        return PvAtom::Int(i);
     }
+    if let Ok(f) = s.parse::<f64>() {
+        return PvAtom::Float(f);
+    }
+    parse_atom(s)
 }
 
 fn parse_atom(tok: &str) -> PvAtom {
@@ -310,4 +613,141 @@ fn parse_atom(tok: &str) -> PvAtom {
         return PvAtom::Float(f);
     }
     PvAtom::Text(t.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> PvParams {
+        parse_paravision_params(s.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_scalar_array_and_str() {
+        let mut params = HashMap::new();
+        params.insert("NR".to_string(), PvValue::Scalar(PvAtom::Int(4)));
+        params.insert(
+            "ACQ_size".to_string(),
+            PvValue::Array { dims: vec![2], items: vec![PvAtom::Int(128), PvAtom::Int(64)] },
+        );
+        params.insert("ACQ_method".to_string(), PvValue::Str("FLASH".to_string()));
+        params.insert(
+            "ACQ_empty".to_string(),
+            PvValue::Array { dims: vec![0], items: vec![] },
+        );
+
+        let mut meta = HashMap::new();
+        meta.insert("TITLE".to_string(), "synthetic".to_string());
+        meta.insert("JCAMPDX".to_string(), "4.24".to_string());
+
+        let original = PvParams { meta, params };
+        let round_tripped = parse(&original.to_jcamp_string());
+
+        assert_eq!(round_tripped, original);
+    }
+
+    #[test]
+    fn push_atoms_from_line_fast_splits_on_spaces_and_tabs() {
+        let parsed = parse("##$ARR=( 3 )\n1\t2   3\n");
+        let items = parsed.params.get("ARR").unwrap().to_vec_i64().unwrap();
+        assert_eq!(items, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn unexpected_eof_reports_the_dims_header_line_and_counts() {
+        // Header on line 2, one of the two elements missing, then EOF.
+        let err = parse_paravision_params("##TITLE=x\n##$ARR=( 2 )\n1\n".as_bytes()).unwrap_err();
+        match err {
+            PvError::UnexpectedEof { key, line, expected, got } => {
+                assert_eq!(key, "ARR");
+                assert_eq!(line, 2);
+                assert_eq!(expected, 2);
+                assert_eq!(got, 1);
+            }
+            other => panic!("expected UnexpectedEof, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bad_dims_reports_the_header_line_and_raw_text() {
+        let err = parse_paravision_params("##TITLE=x\n##$ARR=( oops )\n".as_bytes()).unwrap_err();
+        match err {
+            PvError::BadDims { key, line, raw } => {
+                assert_eq!(key, "ARR");
+                assert_eq!(line, 2);
+                assert_eq!(raw, "( oops )");
+            }
+            other => panic!("expected BadDims, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn short_array_reports_the_dims_header_line_and_counts() {
+        // A new record starts before ARR's 3 promised elements are filled.
+        let err = parse_paravision_params("##$ARR=( 3 )\n1 2\n##$NEXT=5\n".as_bytes()).unwrap_err();
+        match err {
+            PvError::ShortArray { key, line, need, got } => {
+                assert_eq!(key, "ARR");
+                assert_eq!(line, 1);
+                assert_eq!(need, 3);
+                assert_eq!(got, 2);
+            }
+            other => panic!("expected ShortArray, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn param_reader_stops_without_parsing_the_rest_of_the_file() {
+        let input = "##$A=1\n##$B=( 1000000000 )\nbogus\n".to_string();
+        let mut rdr = ParamReader::new(input.as_bytes());
+        let (key, value) = rdr.next().unwrap().unwrap();
+        assert_eq!(key, "A");
+        assert_eq!(value, PvValue::Scalar(PvAtom::Int(1)));
+        // Dropping `rdr` here never touches B's declared billion elements.
+    }
+
+    #[test]
+    fn get_nd_indexes_row_major_and_rejects_bad_coords() {
+        let arr = PvValue::Array {
+            dims: vec![2, 3],
+            items: (0..6).map(PvAtom::Int).collect(),
+        };
+        assert_eq!(arr.get_nd(&[0, 0]), Some(&PvAtom::Int(0)));
+        assert_eq!(arr.get_nd(&[1, 2]), Some(&PvAtom::Int(5)));
+        assert_eq!(arr.get_nd(&[1, 3]), None); // out of range
+        assert_eq!(arr.get_nd(&[0]), None); // rank mismatch
+        assert_eq!(PvValue::Scalar(PvAtom::Int(1)).get_nd(&[0]), None);
+    }
+
+    #[test]
+    fn rows_chunks_a_rank_2_array_and_rejects_other_ranks() {
+        let arr = PvValue::Array {
+            dims: vec![2, 3],
+            items: (0..6).map(PvAtom::Int).collect(),
+        };
+        let rows: Vec<&[PvAtom]> = arr.rows().unwrap().collect();
+        assert_eq!(rows, vec![
+            &[PvAtom::Int(0), PvAtom::Int(1), PvAtom::Int(2)][..],
+            &[PvAtom::Int(3), PvAtom::Int(4), PvAtom::Int(5)][..],
+        ]);
+
+        let rank1 = PvValue::Array { dims: vec![6], items: (0..6).map(PvAtom::Int).collect() };
+        assert!(rank1.rows().is_none());
+    }
+
+    #[test]
+    fn chunks_by_last_dim_groups_flattened_items_and_rejects_zero_last_dim() {
+        let arr = PvValue::Array {
+            dims: vec![2, 2, 3],
+            items: (0..12).map(PvAtom::Int).collect(),
+        };
+        let chunks: Vec<&[PvAtom]> = arr.chunks_by_last_dim().unwrap().collect();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], &[PvAtom::Int(0), PvAtom::Int(1), PvAtom::Int(2)][..]);
+
+        let empty_last = PvValue::Array { dims: vec![3, 0], items: vec![] };
+        assert!(empty_last.chunks_by_last_dim().is_none());
+        assert!(PvValue::Scalar(PvAtom::Int(1)).chunks_by_last_dim().is_none());
+    }
 }
\ No newline at end of file
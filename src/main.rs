@@ -9,14 +9,14 @@ fn main() {
     let pv = parse_paravision_params(BufReader::new(f)).unwrap();
 
     let (n_read,n_proj):(usize,usize) = if let PvValue::Array {items,..} = pv.params.get("ACQ_size").unwrap() {
-        (items[0].clone().into(),items[1].clone().into())
+        (items[0].clone().try_into().unwrap(),items[1].clone().try_into().unwrap())
     }else {
         panic!("failed to parse ACQ_size");
     };
 
     // this is the number of frames
     let n_frames:usize = if let PvValue::Scalar(nr) = pv.params.get("NR").unwrap() {
-        nr.clone().into()
+        nr.clone().try_into().unwrap()
     }else {
         panic!("failed to parse number of frames");
     };